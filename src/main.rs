@@ -1,10 +1,13 @@
 use anyhow::{Context, Result, anyhow, bail};
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use env_logger::Env;
 use git2::{Oid, Repository, Time};
+use rayon::prelude::*;
 use serde::Serialize;
+use sha1::Sha1;
 use sha2::{Digest, Sha256};
 use std::{
+    cell::RefCell,
     collections::{BTreeMap, BTreeSet},
     fs::File,
     io::{BufRead, BufReader, Read},
@@ -12,6 +15,35 @@ use std::{
 };
 use walkdir::WalkDir;
 
+/// Hashing scheme used for the offline snapshot's files.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum HashScheme {
+    /// Hash each file the same way git hashes a blob, so it can be compared directly against
+    /// commit tree Oids with no re-hashing on the repo side.
+    GitBlob,
+    /// SHA-256 fallback for non-git-style comparisons. Costs a blob read per file on the repo
+    /// side (the Oid alone can't produce it), unlike the zero-IO `git-blob` scheme.
+    Sha256,
+}
+
+/// How `--unchanged-files-hint-list` narrows the commits that get scored.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum HintPruneMode {
+    /// Score only commits where every hint file's git blob matches the offline snapshot. Hint
+    /// files that can't be read from the snapshot are ignored, and if no commit matches all the
+    /// rest, every commit is scored.
+    Filter,
+    /// Score every commit, but put hint-matching ones first.
+    Reorder,
+}
+
+/// The CLI's scan-tuning flags, bundled into one argument.
+struct ScanOptions {
+    offline_hash_scheme: HashScheme,
+    hint_prune_mode: HintPruneMode,
+    near_rename_size_similarity_threshold: Option<f64>,
+}
+
 #[derive(Parser, Debug)]
 #[command(
     name = "commit-similarity-search",
@@ -41,6 +73,23 @@ struct Args {
     /// Path to a text file with files expected to match in closest commit (one per line)
     #[arg(long = "unchanged-files-hint-list")]
     unchanged_files_hint_list_path: Option<PathBuf>,
+
+    /// Hashing scheme for the offline snapshot. `git-blob` matches git's own blob ids (so
+    /// nothing needs to be hashed on the repo side); `sha256` is the legacy fallback.
+    #[arg(long = "offline-hash-scheme", value_enum, default_value_t = HashScheme::GitBlob)]
+    offline_hash_scheme: HashScheme,
+
+    /// How to use the hint list to narrow the commits scanned: `filter` scores only the
+    /// narrowed candidates, `reorder` scans everything but checks candidates first.
+    #[arg(long = "hint-prune-mode", value_enum, default_value_t = HintPruneMode::Reorder)]
+    hint_prune_mode: HintPruneMode,
+
+    /// Size-similarity ratio (0.0-1.0) for flagging a one-sided file pair as a likely near-rename
+    /// once it has no exact content match. Unset by default: it's a size-only heuristic (no
+    /// byte-level diff) checked pairwise over every one-sided file on both sides per commit, so
+    /// it reintroduces per-commit disk stats and scales O(one-sided files squared).
+    #[arg(long = "near-rename-size-similarity-threshold")]
+    near_rename_size_similarity_threshold: Option<f64>,
 }
 
 #[derive(Serialize)]
@@ -54,6 +103,7 @@ struct DataRow<'a> {
     mismatches: usize,
     one_sided_files: usize,
     total_matched_hashes: usize,
+    renames: usize,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     matched_hint_files: Option<usize>,
@@ -109,13 +159,19 @@ fn main() -> Result<()> {
         None => None,
     };
 
+    let scan_options = ScanOptions {
+        offline_hash_scheme: args.offline_hash_scheme,
+        hint_prune_mode: args.hint_prune_mode,
+        near_rename_size_similarity_threshold: args.near_rename_size_similarity_threshold,
+    };
+
     let (best_commit, best_score) = find_most_similar_commit(
         &repo,
-        &args.git_repo_path,
         &args.non_git_folder_path,
         args.jsonl_output_path.as_deref(),
         &commits,
         hint_list.as_deref(),
+        &scan_options,
     )?;
 
     match best_commit {
@@ -145,8 +201,59 @@ fn file_hash(path: &Path) -> Result<String> {
     Ok(format!("{:x}", h.finalize()))
 }
 
+/// Hash a tree blob the same way the offline snapshot was hashed under `scheme`, so both sides
+/// are always directly comparable. `git-blob` is free (the Oid from the tree walk *is* the
+/// hash); `sha256` has to fetch the blob's content, since it can't be derived from the Oid alone.
+fn git_blob_hash_string(repo: &Repository, oid: Oid, scheme: HashScheme) -> Result<String> {
+    match scheme {
+        HashScheme::GitBlob => Ok(oid.to_string()),
+        HashScheme::Sha256 => {
+            let blob = repo.find_blob(oid)?;
+            let mut h = Sha256::new();
+            h.update(blob.content());
+            Ok(format!("{:x}", h.finalize()))
+        }
+    }
+}
+
+/// Calculate a file's git blob object id: `SHA1("blob " + content_len + "\0" + content)`,
+/// streamed in chunks just like `file_hash`. Matches what `git hash-object` would produce,
+/// so it can be compared directly against a commit tree's blob Oids.
+fn file_git_blob_id(path: &Path) -> Result<Oid> {
+    let mut f = File::open(path)?;
+    let content_len = f.metadata()?.len();
+
+    let mut h = Sha1::new();
+    h.update(format!("blob {content_len}\0"));
+    let mut buf = vec![0u8; 1 << 20];
+    loop {
+        let n = f.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        h.update(&buf[..n]);
+    }
+    Ok(Oid::from_bytes(&h.finalize())?)
+}
+
+/// Recursively collect `path -> blob Oid` for every file in a commit's tree, straight from the
+/// object database. Never touches the working directory, so scanning a commit no longer requires
+/// checking it out.
+fn collect_tree_blob_ids(tree: &git2::Tree) -> Result<BTreeMap<String, Oid>> {
+    let mut hashes = BTreeMap::new();
+    tree.walk(git2::TreeWalkMode::PreOrder, |root, entry| {
+        if entry.kind() == Some(git2::ObjectType::Blob) {
+            if let Some(name) = entry.name() {
+                hashes.insert(format!("{root}{name}"), entry.id());
+            }
+        }
+        git2::TreeWalkResult::Ok
+    })?;
+    Ok(hashes)
+}
+
 /// Collect file hashes for a directory tree, skipping anything with ".git" in its path components.
-fn collect_file_hashes(base_dir: &Path) -> Result<BTreeMap<String, String>> {
+fn collect_file_hashes(base_dir: &Path, scheme: HashScheme) -> Result<BTreeMap<String, String>> {
     let mut hashes = BTreeMap::new();
     for entry in WalkDir::new(base_dir)
         .into_iter()
@@ -160,36 +267,135 @@ fn collect_file_hashes(base_dir: &Path) -> Result<BTreeMap<String, String>> {
             }
             let rel = pathdiff::diff_paths(path, base_dir).unwrap_or_else(|| path.to_path_buf());
             let relp = rel.to_string_lossy().replace('\\', "/");
-            let h = file_hash(path)?;
+            let h = match scheme {
+                HashScheme::GitBlob => file_git_blob_id(path)?.to_string(),
+                HashScheme::Sha256 => file_hash(path)?,
+            };
             hashes.insert(relp, h);
         }
     }
     Ok(hashes)
 }
 
-/// Compare two directories represented by file-hash maps.
+/// Result of diffing a commit's tree against the offline snapshot.
+struct DirComparison {
+    matches: usize,
+    mismatches: usize,
+    one_sided_files: usize,
+    total_matched_hashes: usize,
+    renames: usize,
+}
+
+/// Compare a commit's tree (path -> blob Oid) against the offline snapshot's file hashes. Each
+/// blob is hashed under `scheme` so it's comparable to the offline side no matter which scheme
+/// the snapshot was hashed with.
+///
+/// Paths that only exist on one side are matched up diffcore-rename-style before being counted
+/// as `one_sided_files`: first by exact content (same blob id at a different path), then,
+/// if `near_rename_size_similarity_threshold` is set, by file size as a cheap stand-in for a
+/// byte-level similarity ratio (a full diff would mean reading blob content for every leftover
+/// file, undoing the point of scoring straight from tree objects).
 fn compare_dirs(
-    hashes_git: &BTreeMap<String, String>,
+    repo: &Repository,
+    hashes_git: &BTreeMap<String, Oid>,
     hashes_offline: &BTreeMap<String, String>,
-) -> (usize, usize, usize, usize) {
+    non_git_folder_path: &Path,
+    scheme: HashScheme,
+    near_rename_size_similarity_threshold: Option<f64>,
+) -> Result<DirComparison> {
+    // Hash every git-side blob under `scheme` up front, so the rest of this function can compare
+    // plain strings without caring whether the offline side was hashed as a git blob or SHA-256.
+    let git_hashes: BTreeMap<String, String> = hashes_git
+        .iter()
+        .map(|(p, &oid)| Ok((p.clone(), git_blob_hash_string(repo, oid, scheme)?)))
+        .collect::<Result<_>>()?;
+
     let git_keys: BTreeSet<_> = hashes_git.keys().collect();
     let off_keys: BTreeSet<_> = hashes_offline.keys().collect();
     let common: BTreeSet<_> = git_keys.intersection(&off_keys).collect();
 
     let mut matches = 0usize;
     for f in &common {
-        if hashes_git.get(**f) == hashes_offline.get(**f) {
+        if git_hashes.get(**f).map(String::as_str) == hashes_offline.get(**f).map(String::as_str) {
             matches += 1;
         }
     }
     let mismatches = common.len() - matches;
-    let one_sided_files = git_keys.symmetric_difference(&off_keys).count();
 
-    let git_vals: BTreeSet<_> = hashes_git.values().collect();
-    let off_vals: BTreeSet<_> = hashes_offline.values().collect();
-    let total_matched_hashes = git_vals.intersection(&off_vals).count();
+    let git_vals: BTreeSet<&str> = git_hashes.values().map(String::as_str).collect();
+    let off_vals: BTreeSet<&str> = hashes_offline.values().map(String::as_str).collect();
+    let total_matched_hashes = git_vals.iter().filter(|v| off_vals.contains(*v)).count();
+
+    let git_only: Vec<&String> = git_keys.difference(&off_keys).copied().collect();
+    let off_only: Vec<&String> = off_keys.difference(&git_keys).copied().collect();
+
+    let mut git_by_hash: BTreeMap<String, Vec<&String>> = BTreeMap::new();
+    for &p in &git_only {
+        git_by_hash.entry(git_hashes[p].clone()).or_default().push(p);
+    }
+    let mut off_by_hash: BTreeMap<String, Vec<&String>> = BTreeMap::new();
+    for &p in &off_only {
+        off_by_hash.entry(hashes_offline[p].clone()).or_default().push(p);
+    }
+
+    let mut matched_git: BTreeSet<&String> = BTreeSet::new();
+    let mut matched_off: BTreeSet<&String> = BTreeSet::new();
+    let mut renames = 0usize;
+    // Exact-hash renames are identical-content files that just moved path, so they count
+    // towards `matches` the same as a same-path hit would. Near-renames (size heuristic,
+    // below) are weaker guesses and stay score-neutral: `renames` only, no `matches` bump.
+    for (hash, git_paths) in &git_by_hash {
+        if let Some(off_paths) = off_by_hash.get(hash) {
+            for (g, o) in git_paths.iter().zip(off_paths.iter()) {
+                matched_git.insert(g);
+                matched_off.insert(o);
+                renames += 1;
+                matches += 1;
+            }
+        }
+    }
+
+    if let Some(threshold) = near_rename_size_similarity_threshold {
+        for &g in &git_only {
+            if matched_git.contains(g) {
+                continue;
+            }
+            let Ok(git_size) = repo.find_blob(hashes_git[g]).map(|b| b.size() as u64) else {
+                continue;
+            };
+            for &o in &off_only {
+                if matched_off.contains(o) {
+                    continue;
+                }
+                let Ok(off_size) = std::fs::metadata(non_git_folder_path.join(o)).map(|m| m.len())
+                else {
+                    continue;
+                };
+                let max_size = git_size.max(off_size);
+                let similarity = if max_size == 0 {
+                    1.0
+                } else {
+                    1.0 - (git_size.abs_diff(off_size) as f64 / max_size as f64)
+                };
+                if similarity >= threshold {
+                    matched_git.insert(g);
+                    matched_off.insert(o);
+                    renames += 1;
+                    break;
+                }
+            }
+        }
+    }
 
-    (matches, mismatches, one_sided_files, total_matched_hashes)
+    let one_sided_files = git_only.len() + off_only.len() - matched_git.len() - matched_off.len();
+
+    Ok(DirComparison {
+        matches,
+        mismatches,
+        one_sided_files,
+        total_matched_hashes,
+        renames,
+    })
 }
 
 /// Traverse all commits reachable from any ref (like `git rev-list --all`), newest-first.
@@ -289,37 +495,275 @@ fn resolve_master_like_ref(repo: &Repository) -> Result<String> {
     Err(anyhow!("No master-like ref found"))
 }
 
-/// True if `commit` is an ancestor of `master_ref` (master/main line).
-fn is_commit_in_master_lineage(repo: &Repository, commit: Oid, master_ref: &str) -> Result<bool> {
+/// All commits reachable from `master_ref`, computed once via a single revwalk so membership
+/// checks in the hot loop are an O(log n) set lookup instead of an O(commits) ancestry query per
+/// commit. (libgit2's safe bindings don't expose commit-graph generation numbers, so this doesn't
+/// get the extra short-circuit a native `git` build would get from a commit-graph file; the
+/// revwalk itself is the real fix for the quadratic cost.)
+fn compute_master_lineage(repo: &Repository, master_ref: &str) -> Result<BTreeSet<Oid>> {
     let head_oid = repo.refname_to_id(master_ref)?;
-    // â€œA is ancestor of Bâ€ â‰ˆ graph_descendant_of(B, A)
-    Ok(repo.graph_descendant_of(head_oid, commit).unwrap_or(false))
+    let mut walk = repo.revwalk()?;
+    walk.push(head_oid)?;
+    walk.map(|oid| oid.map_err(Into::into)).collect()
 }
 
-fn check_out_commit(repo: &Repository, commit_oid: Oid) -> Result<()> {
-    let commit = repo.find_commit(commit_oid)?;
-    let tree = commit.tree()?;
-    repo.checkout_tree(&tree.as_object(), None)?;
-    repo.set_head_detached(commit_oid)?;
-    Ok(())
+/// A commit's score and row data, computed independently of any other commit.
+struct ScoredCommit {
+    commit_num: usize,
+    commit_hash: String,
+    datetime: String,
+    score: i64,
+    in_master_lineage: bool,
+    matches: usize,
+    mismatches: usize,
+    one_sided_files: usize,
+    total_matched_hashes: usize,
+    renames: usize,
+    matched_hint_files: Option<usize>,
+}
+
+/// Run `f` against a `Repository` handle private to the calling thread, opening one the first
+/// time a given worker thread needs it. A single `Repository` can't be shared across threads, so
+/// each rayon worker gets (and reuses) its own.
+fn with_thread_repo<T>(repo_path: &Path, f: impl FnOnce(&Repository) -> Result<T>) -> Result<T> {
+    thread_local! {
+        static REPO: RefCell<Option<Repository>> = const { RefCell::new(None) };
+    }
+    REPO.with(|cell| {
+        let mut slot = cell.borrow_mut();
+        if slot.is_none() {
+            *slot = Some(Repository::open(repo_path)?);
+        }
+        f(slot.as_ref().expect("just populated above"))
+    })
+}
+
+/// Everything about a scan that's constant across commits.
+struct ScanContext<'a> {
+    repo_path: &'a Path,
+    non_git_folder_path: &'a Path,
+    master_lineage: &'a BTreeSet<Oid>,
+    hashes_offline: &'a BTreeMap<String, String>,
+    offline_hash_scheme: HashScheme,
+    unchanged_files_hint_list: Option<&'a [String]>,
+    near_rename_size_similarity_threshold: Option<f64>,
+}
+
+/// Score a single commit against the offline snapshot. Self-contained so it can run on any
+/// rayon worker thread: opens its own thread-local `Repository` handle rather than sharing one.
+fn score_commit(ctx: &ScanContext, commit_num: usize, commit_oid: Oid) -> Result<ScoredCommit> {
+    with_thread_repo(ctx.repo_path, |repo| {
+        let c = repo.find_commit(commit_oid)?;
+        let commit_hash = c.id().to_string();
+        let datetime = time_to_iso8601(c.time());
+
+        // Score against the commit's tree directly from the object database; no checkout needed.
+        let hashes_git = collect_tree_blob_ids(&c.tree()?)?;
+        let comparison = compare_dirs(
+            repo,
+            &hashes_git,
+            ctx.hashes_offline,
+            ctx.non_git_folder_path,
+            ctx.offline_hash_scheme,
+            ctx.near_rename_size_similarity_threshold,
+        )?;
+        let DirComparison {
+            matches,
+            mismatches,
+            one_sided_files,
+            total_matched_hashes,
+            renames,
+        } = comparison;
+
+        let score = (matches as i64) - (mismatches as i64) - (one_sided_files as i64);
+
+        let in_master_lineage = ctx.master_lineage.contains(&commit_oid);
+
+        let matched_hint_files = ctx
+            .unchanged_files_hint_list
+            .map(|hint_list| -> Result<usize> {
+                let mut count = 0;
+                for f in hint_list {
+                    let lhs = match hashes_git.get(f) {
+                        Some(&oid) => git_blob_hash_string(repo, oid, ctx.offline_hash_scheme)?,
+                        None => "GIT_HASH_NOT_EXIST".to_string(),
+                    };
+                    let rhs = ctx
+                        .hashes_offline
+                        .get(f)
+                        .map(String::as_str)
+                        .unwrap_or("OFFLINE_HASH_NOT_EXIST");
+                    if lhs == rhs {
+                        count += 1;
+                    }
+                }
+                Ok(count)
+            })
+            .transpose()?;
+
+        Ok(ScoredCommit {
+            commit_num,
+            commit_hash,
+            datetime,
+            score,
+            in_master_lineage,
+            matches,
+            mismatches,
+            one_sided_files,
+            total_matched_hashes,
+            renames,
+            matched_hint_files,
+        })
+    })
+}
+
+/// Commits (out of `commits`) whose tree has `hint_path` pointing at `offline_blob_id`, found by
+/// looking up just that one path per commit rather than scoring the whole tree.
+fn commits_matching_hint_file(
+    repo_path: &Path,
+    commits: &[Oid],
+    hint_path: &str,
+    offline_blob_id: Oid,
+) -> Result<BTreeSet<Oid>> {
+    commits
+        .par_iter()
+        .filter_map(|&commit_oid| {
+            let is_match = with_thread_repo(repo_path, |repo| {
+                let tree = repo.find_commit(commit_oid)?.tree()?;
+                Ok(tree
+                    .get_path(Path::new(hint_path))
+                    .map(|entry| entry.id() == offline_blob_id)
+                    .unwrap_or(false))
+            });
+            match is_match {
+                Ok(true) => Some(Ok(commit_oid)),
+                Ok(false) => None,
+                Err(e) => Some(Err(e)),
+            }
+        })
+        .collect::<Result<BTreeSet<_>>>()
+}
+
+/// Narrow `commits` (each tagged with its index in the original, newest-first commit list, which
+/// travels along so reordering here can't change a commit's reported `commit_number`) using the
+/// hint list's git-blob history: intersect, across hint files, the sets of commits whose tree
+/// still matches the offline snapshot at that path. The true closest commit is very likely inside
+/// (or adjacent to) that intersection, so the expensive full scan only needs to run over the
+/// narrowed window.
+fn narrow_commits_by_hints(
+    repo_path: &Path,
+    commits: &[(usize, Oid)],
+    non_git_folder_path: &Path,
+    hint_list: &[String],
+    mode: HintPruneMode,
+) -> Result<Vec<(usize, Oid)>> {
+    let oids: Vec<Oid> = commits.iter().map(|&(_, oid)| oid).collect();
+
+    let mut candidates: Option<BTreeSet<Oid>> = None;
+    for hint_path in hint_list {
+        let offline_blob_id = match file_git_blob_id(&non_git_folder_path.join(hint_path)) {
+            Ok(id) => id,
+            Err(e) => {
+                log::warn!("Skipping hint file \"{hint_path}\" for candidate narrowing: {e}");
+                continue;
+            }
+        };
+        let matching = commits_matching_hint_file(repo_path, &oids, hint_path, offline_blob_id)?;
+        candidates = Some(match candidates {
+            Some(acc) => acc.intersection(&matching).copied().collect(),
+            None => matching,
+        });
+    }
+
+    let Some(candidates) = candidates.filter(|c| !c.is_empty()) else {
+        log::info!("Hint list did not narrow the candidate set; scanning all commits.");
+        return Ok(commits.to_vec());
+    };
+
+    match mode {
+        HintPruneMode::Filter => {
+            let narrowed: Vec<(usize, Oid)> = commits
+                .iter()
+                .copied()
+                .filter(|&(_, oid)| candidates.contains(&oid))
+                .collect();
+            log::info!(
+                "Hint list narrowed the scan from {} to {} commits.",
+                commits.len(),
+                narrowed.len()
+            );
+            Ok(narrowed)
+        }
+        HintPruneMode::Reorder => {
+            let (front, back): (Vec<_>, Vec<_>) = commits
+                .iter()
+                .copied()
+                .partition(|&(_, oid)| candidates.contains(&oid));
+            log::info!(
+                "Hint list matched {} of {} commits; scanning those first.",
+                front.len(),
+                commits.len()
+            );
+            Ok([front, back].concat())
+        }
+    }
 }
 
 fn find_most_similar_commit(
     repo: &Repository,
-    git_repo_path: &Path,
     non_git_folder_path: &Path,
     jsonl_output_path: Option<&Path>,
     commits: &[Oid],
     unchanged_files_hint_list: Option<&[String]>,
+    options: &ScanOptions,
 ) -> Result<(Option<String>, i64)> {
     let master_ref = resolve_master_like_ref(repo)?;
     log::info!(
         "Using \"{}\" as the master-like lineage root.",
         master_ref
     );
+    let master_lineage = compute_master_lineage(repo, &master_ref)?;
 
     // Prepare offline hashes.
-    let hashes_offline = collect_file_hashes(&non_git_folder_path)?;
+    let hashes_offline = collect_file_hashes(non_git_folder_path, options.offline_hash_scheme)?;
+
+    // Each commit is scored independently of the others now that scoring reads straight from
+    // the object database, so the whole scan can run across a rayon thread pool.
+    let repo_path = repo.path().to_path_buf();
+
+    // Each commit keeps its index from the original, newest-first `commits` list attached so
+    // that `--hint-prune-mode reorder` only changes scan order, never `commit_number` or the
+    // tie-break order used below.
+    let indexed_commits: Vec<(usize, Oid)> = commits.iter().copied().enumerate().collect();
+
+    let commits_to_scan = match unchanged_files_hint_list {
+        Some(hint_list) if !hint_list.is_empty() => narrow_commits_by_hints(
+            &repo_path,
+            &indexed_commits,
+            non_git_folder_path,
+            hint_list,
+            options.hint_prune_mode,
+        )?,
+        _ => indexed_commits,
+    };
+
+    let ctx = ScanContext {
+        repo_path: &repo_path,
+        non_git_folder_path,
+        master_lineage: &master_lineage,
+        hashes_offline: &hashes_offline,
+        offline_hash_scheme: options.offline_hash_scheme,
+        unchanged_files_hint_list,
+        near_rename_size_similarity_threshold: options.near_rename_size_similarity_threshold,
+    };
+
+    let mut scored: Vec<ScoredCommit> = commits_to_scan
+        .par_iter()
+        .map(|&(commit_num, commit_oid)| score_commit(&ctx, commit_num, commit_oid))
+        .collect::<Result<Vec<_>>>()?;
+    // `commits_to_scan` may be reordered by hint-list pruning; restore original newest-first
+    // order so `best`/output and tie-break behavior are unaffected by `--hint-prune-mode`.
+    scored.sort_by_key(|row| row.commit_num);
 
     let mut best_score: i64 = i64::MIN;
     let mut best_commit: Option<String> = None;
@@ -334,57 +778,32 @@ fn find_most_similar_commit(
         None => None,
     };
 
-    for (commit_num, &commit_oid) in commits.iter().enumerate() {
-        let c = repo.find_commit(commit_oid)?;
-        let commit_hash = c.id().to_string();
-        let commit_time = time_to_iso8601(c.time());
-
-        // Check out the commit (detached HEAD).
-        check_out_commit(repo, commit_oid)?;
-
-        let hashes_git = collect_file_hashes(git_repo_path)?;
-        let (matches, mismatches, one_sided_files, total_matched_hashes) =
-            compare_dirs(&hashes_git, &hashes_offline);
-
-        let score = (matches as i64) - (mismatches as i64) - (one_sided_files as i64);
-
-        let in_master_lineage = is_commit_in_master_lineage(repo, commit_oid, &master_ref)?;
-
-        let matched_hint_files = unchanged_files_hint_list.map(|hint_list| {
-            hint_list
-                .iter()
-                .filter(|f| {
-                    let lhs_default_str_binding = "GIT_HASH_NOT_EXIST".to_string();
-                    let lhs = hashes_git.get(*f).unwrap_or(&lhs_default_str_binding);
-                    let rhs_default_str_binding = "OFFLINE_HASH_NOT_EXIST".to_string();
-                    let rhs = hashes_offline.get(*f).unwrap_or(&rhs_default_str_binding);
-                    lhs == rhs
-                })
-                .count()
-        });
-
+    // Walk the scores in original commit-number order so `best`/output ordering match the old
+    // sequential scan exactly, regardless of what order the hint list scanned them in.
+    for row in &scored {
         let mut best = None;
-        if score > best_score {
-            best_score = score;
-            best_commit = Some(commit_hash.clone());
+        if row.score > best_score {
+            best_score = row.score;
+            best_commit = Some(row.commit_hash.clone());
             best = Some("NEW BEST ðŸŸ¢");
         }
 
-        let row = DataRow {
-            commit_number: commit_num + 1,
-            commit_hash: &commit_hash,
-            datetime: commit_time,
-            score,
-            in_master_lineage,
-            matches,
-            mismatches,
-            one_sided_files,
-            total_matched_hashes,
-            matched_hint_files,
+        let data_row = DataRow {
+            commit_number: row.commit_num + 1,
+            commit_hash: &row.commit_hash,
+            datetime: row.datetime.clone(),
+            score: row.score,
+            in_master_lineage: row.in_master_lineage,
+            matches: row.matches,
+            mismatches: row.mismatches,
+            one_sided_files: row.one_sided_files,
+            total_matched_hashes: row.total_matched_hashes,
+            renames: row.renames,
+            matched_hint_files: row.matched_hint_files,
             best,
         };
 
-        let line = serde_json::to_string(&row)?;
+        let line = serde_json::to_string(&data_row)?;
         log::info!("{}", line);
         if let Some(f) = jsonl_file.as_mut() {
             use std::io::Write;
@@ -413,3 +832,133 @@ fn time_to_iso8601(t: Time) -> String {
         })
         .to_rfc3339()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    /// Commit `files` as an orphan commit (no parent) and return its Oid.
+    fn make_commit(repo: &Repository, files: &[(&str, &str)]) -> Oid {
+        let sig = git2::Signature::now("test", "test@example.com").unwrap();
+        let mut builder = repo.treebuilder(None).unwrap();
+        for (name, content) in files {
+            let oid = repo.blob(content.as_bytes()).unwrap();
+            builder.insert(name, oid, 0o100644).unwrap();
+        }
+        let tree = repo.find_tree(builder.write().unwrap()).unwrap();
+        repo.commit(None, &sig, &sig, "test commit", &tree, &[])
+            .unwrap()
+    }
+
+    /// Reorder mode must score the same commits the same way as no hint list at all; it only
+    /// changes scan order, not which commit wins or what `commit_number` each one reports. This
+    /// pins the regression from `chunk0-4`'s first landing, where `commit_num` tracked position
+    /// in the reordered scan list instead of the original commit index.
+    #[test]
+    fn hint_prune_reorder_matches_no_hint_list_result() {
+        let repo_dir = tempdir().unwrap();
+        let repo = Repository::init(repo_dir.path()).unwrap();
+
+        // `c` and `a` tie in score (one file matches, one doesn't, just on different paths), so
+        // which one wins depends only on scan order if `commit_num` isn't kept stable.
+        let c_oid = make_commit(&repo, &[("data.txt", "foo"), ("hint.txt", "H2")]);
+        let a_oid = make_commit(&repo, &[("data.txt", "bar"), ("hint.txt", "H1")]);
+        repo.reference("refs/heads/master", a_oid, true, "set master")
+            .unwrap();
+
+        let non_git_dir = tempdir().unwrap();
+        std::fs::write(non_git_dir.path().join("data.txt"), "foo").unwrap();
+        std::fs::write(non_git_dir.path().join("hint.txt"), "H1").unwrap();
+
+        let commits = [c_oid, a_oid];
+        let hint_list = vec!["hint.txt".to_string()];
+
+        let no_hint_options = ScanOptions {
+            offline_hash_scheme: HashScheme::GitBlob,
+            hint_prune_mode: HintPruneMode::Reorder,
+            near_rename_size_similarity_threshold: None,
+        };
+        let (baseline_commit, baseline_score) =
+            find_most_similar_commit(&repo, non_git_dir.path(), None, &commits, None, &no_hint_options)
+                .unwrap();
+
+        let reorder_options = ScanOptions {
+            offline_hash_scheme: HashScheme::GitBlob,
+            hint_prune_mode: HintPruneMode::Reorder,
+            near_rename_size_similarity_threshold: None,
+        };
+        let (reorder_commit, reorder_score) = find_most_similar_commit(
+            &repo,
+            non_git_dir.path(),
+            None,
+            &commits,
+            Some(&hint_list),
+            &reorder_options,
+        )
+        .unwrap();
+
+        assert_eq!(baseline_commit, Some(c_oid.to_string()));
+        assert_eq!(reorder_commit, baseline_commit);
+        assert_eq!(reorder_score, baseline_score);
+    }
+
+    #[test]
+    fn compare_dirs_counts_exact_renames_as_matches() {
+        let repo_dir = tempdir().unwrap();
+        let repo = Repository::init(repo_dir.path()).unwrap();
+        let blob_oid = repo.blob(b"same content").unwrap();
+
+        let hashes_git: BTreeMap<String, Oid> =
+            [("old/name.txt".to_string(), blob_oid)].into_iter().collect();
+        let hashes_offline: BTreeMap<String, String> =
+            [("new/name.txt".to_string(), blob_oid.to_string())]
+                .into_iter()
+                .collect();
+
+        let comparison = compare_dirs(
+            &repo,
+            &hashes_git,
+            &hashes_offline,
+            repo_dir.path(),
+            HashScheme::GitBlob,
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(comparison.renames, 1);
+        assert_eq!(comparison.matches, 1);
+        assert_eq!(comparison.mismatches, 0);
+        assert_eq!(comparison.one_sided_files, 0);
+    }
+
+    #[test]
+    fn compare_dirs_near_renames_stay_score_neutral() {
+        let repo_dir = tempdir().unwrap();
+        let repo = Repository::init(repo_dir.path()).unwrap();
+        let blob_oid = repo.blob(&[b'a'; 100]).unwrap();
+
+        let hashes_git: BTreeMap<String, Oid> =
+            [("old/name.txt".to_string(), blob_oid)].into_iter().collect();
+
+        std::fs::write(repo_dir.path().join("new_name.txt"), [b'b'; 95]).unwrap();
+        let hashes_offline: BTreeMap<String, String> =
+            [("new_name.txt".to_string(), "unrelated-content-hash".to_string())]
+                .into_iter()
+                .collect();
+
+        let comparison = compare_dirs(
+            &repo,
+            &hashes_git,
+            &hashes_offline,
+            repo_dir.path(),
+            HashScheme::GitBlob,
+            Some(0.9),
+        )
+        .unwrap();
+
+        assert_eq!(comparison.renames, 1);
+        assert_eq!(comparison.matches, 0);
+        assert_eq!(comparison.one_sided_files, 0);
+    }
+}